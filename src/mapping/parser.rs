@@ -43,7 +43,7 @@ impl<'a> Parser<'a> {
         }
     }
 
-    pub fn next_token(&mut self) -> Option<Result<Token, Error>> {
+    pub fn next_token(&mut self) -> Option<Result<Token<'a>, Error<'a>>> {
         if self.pos >= self.data.len() {
             None
         } else {
@@ -56,7 +56,39 @@ impl<'a> Parser<'a> {
         }
     }
 
-    fn parse_uuid(&mut self) -> Result<Token, Error> {
+    /// Parses `mapping` in recovering mode, collecting every token that parsed successfully
+    /// along with every error encountered instead of stopping at the first one.
+    ///
+    /// `parse_key_val` already leaves `pos` past the offending `key:value` pair before
+    /// returning its error, and leaves `state` at `State::KeyVal`, so a bad mapping entry
+    /// does not stop the parse on its own — this just keeps calling `next_token` and
+    /// records every `Err` instead of returning on the first one. A failure while parsing
+    /// the GUID still transitions to `State::Invalid`, since there is no sensible delimiter
+    /// to resynchronize on, so it ends the parse early. Parsing the name never fails.
+    pub fn parse_all(mapping: &'a str) -> (Vec<Token<'a>>, Vec<Error<'a>>) {
+        let mut parser = Parser::new(mapping);
+        let mut tokens = Vec::new();
+        let mut errors = Vec::new();
+
+        while let Some(result) = parser.next_token() {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(err) => {
+                    let unrecoverable = parser.state == State::Invalid;
+
+                    errors.push(err);
+
+                    if unrecoverable {
+                        break;
+                    }
+                }
+            }
+        }
+
+        (tokens, errors)
+    }
+
+    fn parse_uuid(&mut self) -> Result<Token<'a>, Error<'a>> {
         let next_comma = self.next_comma_or_end();
         let uuid = Uuid::parse_str(&self.data[self.pos..next_comma])
             .map(|uuid| Token::Uuid(uuid))
@@ -76,7 +108,7 @@ impl<'a> Parser<'a> {
         uuid
     }
 
-    fn parse_name(&mut self) -> Result<Token, Error> {
+    fn parse_name(&mut self) -> Result<Token<'a>, Error<'a>> {
         let next_comma = self.next_comma_or_end();
         let name = &self.data[self.pos..next_comma];
 
@@ -86,22 +118,23 @@ impl<'a> Parser<'a> {
         Ok(Token::Name(name))
     }
 
-    fn parse_key_val(&mut self) -> Result<Token, Error> {
+    fn parse_key_val(&mut self) -> Result<Token<'a>, Error<'a>> {
         let next_comma = self.next_comma_or_end();
         let pair = &self.data[self.pos..next_comma];
         let pos = self.pos;
+        let len = next_comma - pos;
         self.pos = next_comma + 1;
 
         let mut split = pair.split(':');
         let key = split
             .next()
-            .ok_or(Error::new(ErrorKind::InvalidKeyValPair, pos))?;
+            .ok_or(Error::spanning(ErrorKind::InvalidKeyValPair, pos, len))?;
         let value = split
             .next()
-            .ok_or(Error::new(ErrorKind::InvalidKeyValPair, pos))?;
+            .ok_or(Error::spanning(ErrorKind::InvalidKeyValPair, pos, len))?;
 
         if split.next().is_some() {
-            return Err(Error::new(ErrorKind::InvalidKeyValPair, pos));
+            return Err(Error::spanning(ErrorKind::InvalidKeyValPair, pos, len));
         }
 
         if key == "platform" {
@@ -151,26 +184,31 @@ impl<'a> Parser<'a> {
             }
             Some("b") => &value[1..],
             Some("h") => {
+                let value_pos = pos + key.len() + 1;
                 let dot_idx = value
                     .find('.')
-                    .ok_or(Error::new(ErrorKind::InvalidValue, pos))?;
+                    .ok_or(Error::spanning(ErrorKind::InvalidValue, pos, len))?;
                 let hat = value[1..dot_idx]
                     .parse()
-                    .or(Err(Error::new(ErrorKind::InvalidValue, pos + 1)))?;
+                    .or(Err(Error::spanning(ErrorKind::InvalidValue, value_pos + 1, dot_idx - 1)))?;
                 let direction = value
                     .get((dot_idx as usize + 1)..)
                     .and_then(|s| s.parse().ok())
-                    .ok_or(Error::new(ErrorKind::InvalidValue, pos + dot_idx + 1))?;
+                    .ok_or(Error::spanning(
+                        ErrorKind::InvalidValue,
+                        value_pos + dot_idx + 1,
+                        value.len() - dot_idx - 1,
+                    ))?;
 
                 let idx = BUTTONS_SDL
                     .binary_search(&key)
-                    .or(Err(Error::new(ErrorKind::UnknownButton, pos)))?;
+                    .map_err(|_| unknown_button_error(key, pos, len))?;
 
                 return Ok(Token::HatMapping { hat, direction, to: BUTTONS[idx] });
             }
-            _ => return Err(Error::new(ErrorKind::InvalidValue, pos)),
+            _ => return Err(Error::spanning(ErrorKind::InvalidValue, pos, len)),
         }.parse::<u16>()
-            .or(Err(Error::new(ErrorKind::InvalidValue, pos)))?;
+            .or(Err(Error::spanning(ErrorKind::InvalidValue, pos, len)))?;
 
         if is_axis {
             let key = match key.get(0..1) {
@@ -189,7 +227,7 @@ impl<'a> Parser<'a> {
 
             let idx = AXES_SDL
                 .binary_search(&key)
-                .or(Err(Error::new(ErrorKind::UnknownAxis, pos)))?;
+                .map_err(|_| unknown_axis_error(key, pos, len))?;
 
             Ok(Token::AxisMapping {
                 from,
@@ -201,7 +239,7 @@ impl<'a> Parser<'a> {
         } else {
             let idx = BUTTONS_SDL
                 .binary_search(&key)
-                .or(Err(Error::new(ErrorKind::UnknownButton, pos)))?;
+                .map_err(|_| unknown_button_error(key, pos, len))?;
 
             Ok(Token::ButtonMapping { from, to: BUTTONS[idx] })
         }
@@ -215,6 +253,72 @@ impl<'a> Parser<'a> {
     }
 }
 
+fn unknown_button_error(key: &str, position: usize, len: usize) -> Error {
+    let mut error = Error::spanning(ErrorKind::UnknownButton, position, len);
+    error.input = Some(key);
+    error.suggestion = closest_match(key, &BUTTONS_SDL);
+    error
+}
+
+fn unknown_axis_error(key: &str, position: usize, len: usize) -> Error {
+    let mut error = Error::spanning(ErrorKind::UnknownAxis, position, len);
+    error.input = Some(key);
+    error.suggestion = closest_match(key, &AXES_SDL);
+    error
+}
+
+// Finds the candidate closest to `key` under Damerau-Levenshtein edit distance, as long as
+// the distance is small enough that the candidate is likely to be what the user meant.
+fn closest_match(key: &str, candidates: &[&'static str]) -> Option<&'static str> {
+    let threshold = ::std::cmp::max(1, key.len() / 3);
+
+    candidates
+        .iter()
+        .map(|&candidate| (candidate, damerau_levenshtein(key, candidate)))
+        .min_by_key(|&(_, distance)| distance)
+        .filter(|&(_, distance)| distance <= threshold)
+        .map(|(candidate, _)| candidate)
+}
+
+// Standard Damerau-Levenshtein DP table: `d[i][j]` is the cost to transform the first `i`
+// chars of `a` into the first `j` chars of `b`, with insertion/deletion/substitution costing
+// 1 and an adjacent transposition also costing 1.
+fn damerau_levenshtein(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let (len_a, len_b) = (a.len(), b.len());
+
+    let mut d = vec![vec![0usize; len_b + 1]; len_a + 1];
+
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..(len_a + 1) {
+        for j in 1..(len_b + 1) {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+
+            d[i][j] = *[
+                d[i - 1][j] + 1,
+                d[i][j - 1] + 1,
+                d[i - 1][j - 1] + cost,
+            ].iter()
+                .min()
+                .unwrap();
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[len_a][len_b]
+}
+
+#[derive(Debug, PartialEq)]
 pub enum Token<'a> {
     Uuid(Uuid),
     Platform(&'a str),
@@ -236,7 +340,99 @@ pub enum Token<'a> {
     },
 }
 
+impl<'a> Token<'a> {
+    /// Writes the SDL representation of this token to `out`, without a trailing or leading
+    /// comma. Use `Serializer` to join several tokens back into a full mapping line.
+    pub fn write_to(&self, out: &mut String) {
+        match *self {
+            Token::Uuid(uuid) => out.push_str(&uuid.to_string().replace('-', "")),
+            Token::Platform(value) => {
+                out.push_str("platform:");
+                out.push_str(value);
+            }
+            Token::Name(name) => out.push_str(name),
+            Token::ButtonMapping { from, to } => {
+                out.push_str(button_sdl_name(to));
+                out.push_str(":b");
+                out.push_str(&from.to_string());
+            }
+            Token::HatMapping { hat, direction, to } => {
+                out.push_str(button_sdl_name(to));
+                out.push_str(":h");
+                out.push_str(&hat.to_string());
+                out.push('.');
+                out.push_str(&direction.to_string());
+            }
+            Token::AxisMapping { from, to, ref input, ref output, inverted } => {
+                match *output {
+                    AxisRange::UpperHalf => out.push('+'),
+                    AxisRange::LowerHalf => out.push('-'),
+                    AxisRange::Full => (),
+                }
+                out.push_str(axis_sdl_name(to));
+                out.push(':');
+
+                match *input {
+                    AxisRange::UpperHalf => out.push_str("+a"),
+                    AxisRange::LowerHalf => out.push_str("-a"),
+                    AxisRange::Full => out.push('a'),
+                }
+                out.push_str(&from.to_string());
+
+                if inverted {
+                    out.push('~');
+                }
+            }
+        }
+    }
+}
+
+fn button_sdl_name(button: Button) -> &'static str {
+    let idx = BUTTONS.iter().position(|&b| b == button).expect("button not in BUTTONS table");
+
+    BUTTONS_SDL[idx]
+}
+
+fn axis_sdl_name(axis: Axis) -> &'static str {
+    let idx = AXES.iter().position(|&a| a == axis).expect("axis not in AXES table");
+
+    AXES_SDL[idx]
+}
+
+/// Joins a stream of `Token`s back into a canonical `guid,name,key:value,...` mapping
+/// string, the inverse of `Parser`.
+pub struct Serializer {
+    buf: String,
+    empty: bool,
+}
+
+impl Default for Serializer {
+    fn default() -> Self {
+        Serializer { buf: String::new(), empty: true }
+    }
+}
+
+impl Serializer {
+    pub fn new() -> Self {
+        Serializer::default()
+    }
+
+    pub fn write_token(&mut self, token: &Token) {
+        if !self.empty {
+            self.buf.push(',');
+        }
+
+        token.write_to(&mut self.buf);
+        self.empty = false;
+    }
+
+    pub fn into_string(self) -> String {
+        self.buf
+    }
+}
+
 #[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AxisRange {
     LowerHalf,
     UpperHalf,
@@ -252,14 +448,58 @@ enum State {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-pub struct Error {
+pub struct Error<'a> {
     position: usize,
     kind: ErrorKind,
+    // The name the user typed, for `ErrorKind::UnknownButton`/`ErrorKind::UnknownAxis`.
+    input: Option<&'a str>,
+    // Only ever set alongside `input`, when a close enough candidate was found.
+    suggestion: Option<&'static str>,
+    // Length of the offending token, when the caller that raised the error knew its bounds.
+    // Lets `render` underline the whole bad token instead of just its starting column.
+    len: Option<usize>,
 }
 
-impl Error {
+impl<'a> Error<'a> {
     pub fn new(kind: ErrorKind, position: usize) -> Self {
-        Error { position, kind }
+        Error { position, kind, input: None, suggestion: None, len: None }
+    }
+
+    /// Like `new`, but also records the length of the offending token so `render` can
+    /// underline the whole span instead of a single column.
+    pub fn spanning(kind: ErrorKind, position: usize, len: usize) -> Self {
+        Error { position, kind, input: None, suggestion: None, len: Some(len) }
+    }
+
+    /// The closest valid button/axis name to the one the user typed, if one was close enough
+    /// to be a likely typo. Only ever `Some` for `ErrorKind::UnknownButton`/`UnknownAxis`.
+    pub fn suggestion(&self) -> Option<&'static str> {
+        self.suggestion
+    }
+
+    /// Renders `source` — the original mapping line this error came from — followed by a
+    /// second line with a caret pointing at `position`, and a tilde span covering the rest
+    /// of the offending token when its length is known, then a third line with the error
+    /// message as printed by `Display`. Mirrors rustc's source-pointing
+    /// diagnostics, for presenting mapping errors in editor/CLI tooling.
+    pub fn render(&self, source: &str) -> String {
+        // `position`/`len` are byte offsets, but the underline is printed in columns, so a
+        // multi-byte UTF-8 char before the error would otherwise throw the caret off; count
+        // chars instead of bytes on both sides of the span.
+        let len = self.len.unwrap_or(1).max(1);
+        let end = (self.position + len).min(source.len());
+
+        let columns = source
+            .get(..self.position)
+            .map_or(self.position, |s| s.chars().count());
+        let span = source
+            .get(self.position..end)
+            .map_or(1, |s| s.chars().count())
+            .max(1);
+
+        let underline = format!("{}^{}", " ".repeat(columns), "~".repeat(span - 1));
+
+        format!("{}\n{}\n{}", source, underline, self)
     }
 }
 
@@ -274,7 +514,7 @@ pub enum ErrorKind {
     UnexpectedEnd,
 }
 
-impl StdError for Error {
+impl<'a> StdError for Error<'a> {
     fn description(&self) -> &str {
         match self.kind {
             ErrorKind::InvalidGuid => "GUID is invalid",
@@ -288,8 +528,58 @@ impl StdError for Error {
     }
 }
 
-impl Display for Error {
+impl<'a> Display for Error<'a> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_fmt(format_args!("{} at {}", self.description(), self.position))
+        match (self.input, self.suggestion) {
+            (Some(input), Some(suggestion)) => f.write_fmt(format_args!(
+                "{} '{}' (did you mean '{}'?) at {}",
+                self.description(),
+                input,
+                suggestion,
+                self.position
+            )),
+            (Some(input), None) => f.write_fmt(format_args!(
+                "{} '{}' at {}",
+                self.description(),
+                input,
+                self.position
+            )),
+            (None, _) => f.write_fmt(format_args!("{} at {}", self.description(), self.position)),
+        }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAPPING: &'static str =
+        "030000005e0400008e02000010010000,Xbox 360 Controller,platform:Linux,a:b0,x:b2,lefttrigger:+a3~,leftx:a4,";
+
+    #[test]
+    fn recovers_from_multiple_bad_key_val_pairs() {
+        let mapping = "030000005e0400008e02000010010000,Xbox 360 Controller,leftshoudler:b1,a:bad,x:b2,";
+        let (tokens, errors) = Parser::parse_all(mapping);
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].suggestion(), Some("leftshoulder"));
+
+        // Uuid, Name, and the surviving "x:b2" button mapping all parsed fine despite the
+        // two bad entries sandwiched between them.
+        assert_eq!(tokens.len(), 3);
+    }
+
+    #[test]
+    fn round_trips_through_serializer() {
+        let (tokens, errors) = Parser::parse_all(MAPPING);
+        assert!(errors.is_empty());
+
+        let mut serializer = Serializer::new();
+        for token in &tokens {
+            serializer.write_token(token);
+        }
+
+        let (tokens_again, errors_again) = Parser::parse_all(&serializer.into_string());
+        assert!(errors_again.is_empty());
+        assert_eq!(tokens, tokens_again);
+    }
+}